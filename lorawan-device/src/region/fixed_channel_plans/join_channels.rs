@@ -1,54 +1,169 @@
 use super::*;
-use core::cmp::Ordering;
 
-#[derive(Clone, Default)]
+/// One entry in an ordered list of preferred join subbands.
+#[derive(Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub(crate) struct JoinChannels {
-    /// The maximum amount of times we attempt to join on the preferred subband.
+struct SubbandBias {
+    subband: Subband,
     max_retries: usize,
-    /// The amount of times we've currently attempted to join on the preferred subband.
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct JoinChannels {
+    /// How much of the `preferred_subbands` retry budget has been spent: one per join attempt,
+    /// plus the whole budget of any entry skipped because its subband had nothing to offer.
     pub num_retries: usize,
-    /// Preferred subband
-    preferred_subband: Option<Subband>,
+    /// Preferred subbands, tried in priority order before falling back to the standard
+    /// compliant rotation.
+    preferred_subbands: heapless::Vec<SubbandBias, 8>,
     /// Channels that have been attempted.
     pub(crate) available_channels: AvailableChannels,
+    /// The datarate this region's 500 kHz "fat bank" channels are used at (DR4 for US915, DR6
+    /// for AU915). Only a join attempted at this exact datarate draws exclusively from the fat
+    /// bank; other datarates still reach it as a wrap target of the standard rotation.
+    join_dr_500khz: DR,
+}
+
+impl Default for JoinChannels {
+    fn default() -> Self {
+        Self {
+            num_retries: 0,
+            preferred_subbands: heapless::Vec::new(),
+            available_channels: AvailableChannels::default(),
+            join_dr_500khz: DR::_4,
+        }
+    }
 }
 
 impl JoinChannels {
     pub(crate) fn set_join_bias(&mut self, subband: Subband, max_retries: usize) {
-        self.preferred_subband = Some(subband);
-        self.max_retries = max_retries;
+        self.preferred_subbands.clear();
+        // a single preferred subband is just a priority list of length one
+        let _ = self.preferred_subbands.push(SubbandBias { subband, max_retries });
+        self.num_retries = 0;
+    }
+
+    /// Specify an ordered list of preferred subbands to try, in priority order, before falling
+    /// back to the standard compliant join rotation. Each subband's retry budget is spent
+    /// before moving on to the next; once the whole list is exhausted, joining proceeds exactly
+    /// as the final-retry handoff from `set_join_bias` does today. At most 8 entries are kept.
+    pub(crate) fn set_join_bias_list(&mut self, subbands: &[(Subband, usize)]) {
+        self.preferred_subbands.clear();
+        for &(subband, max_retries) in subbands {
+            let _ = self.preferred_subbands.push(SubbandBias { subband, max_retries });
+        }
+        self.num_retries = 0;
     }
 
     pub(crate) fn clear_join_bias(&mut self) {
-        self.preferred_subband = None;
-        self.max_retries = 0;
+        self.preferred_subbands.clear();
+        self.num_retries = 0;
+    }
+
+    /// The subband we should currently be drawing from, and the cumulative retry count at which
+    /// its budget is spent and the next entry in the priority list takes over, if `num_retries`
+    /// hasn't yet spent the whole list's budget.
+    fn current_bias_subband(&self) -> Option<(Subband, usize)> {
+        let mut cumulative = 0;
+        for entry in &self.preferred_subbands {
+            cumulative += entry.max_retries;
+            if self.num_retries < cumulative {
+                return Some((entry.subband, cumulative));
+            }
+        }
+        None
     }
 
-    /// To be called after a join accept is received. Resets state for the next join attempt.
+    /// To be called after a join accept is received. Resets state for the next join attempt,
+    /// preserving any user-configured channel blacklist.
     pub(crate) fn reset(&mut self) {
         self.num_retries = 0;
-        self.available_channels = AvailableChannels::default();
-    }
-
-    pub(crate) fn get_next_channel(&mut self, rng: &mut impl RngCore) -> usize {
-        match (self.preferred_subband, self.num_retries.cmp(&self.max_retries)) {
-            (Some(sb), Ordering::Less) => {
-                self.num_retries += 1;
-                // pick a  random number 0-7 on the preferred subband
-                // NB: we don't use 500 kHz channels
-                let channel = (rng.next_u32() as usize % 8) + ((sb as usize - 1) * 8);
-                if self.num_retries == self.max_retries {
-                    // this is our last try with our favorite subband, so will intialize the
-                    // standard join logic with the channel we just tried. This will ensure
-                    // standard and compliant behavior when num_retries is set to 1.
-                    self.available_channels.previous = Some(channel);
-                    self.available_channels.data.set_channel(channel, false);
+        self.available_channels.reset();
+    }
+
+    /// Restrict future join attempts to the channels advertised in a JoinAccept CFList of the
+    /// ChMask variant, so that a lost session doesn't keep probing channels the network has
+    /// already disabled. Falls back to the full (blacklist-respecting) channel plan if the
+    /// advertised mask, once intersected with the user's blacklist, leaves no channel.
+    pub(crate) fn apply_channel_mask(&mut self, mask: &ChannelMask<9>) {
+        self.available_channels.apply_channel_mask(mask);
+    }
+
+    /// Permanently mark a channel as unusable for both join and data channel selection. A no-op
+    /// if it's the last remaining allowed channel, since blacklisting it would leave nothing to
+    /// join on.
+    pub(crate) fn disable_channel(&mut self, channel: usize) {
+        self.available_channels.disable_channel(channel);
+    }
+
+    /// Permanently re-allow a previously blacklisted channel.
+    pub(crate) fn enable_channel(&mut self, channel: usize) {
+        self.available_channels.enable_channel(channel);
+    }
+
+    /// Replace the user's allowed set of channels outright. A no-op if `mask` allows no channel
+    /// that any active network-advertised mask also allows, since that would leave nothing to
+    /// join on.
+    pub(crate) fn set_channel_mask(&mut self, mask: ChannelMask<9>) {
+        self.available_channels.set_base(mask);
+    }
+
+    pub(crate) fn get_next_channel(&mut self, rng: &mut impl RngCore, datarate: DR) -> usize {
+        // walk the priority list, skipping any entry whose subband has nothing left to offer
+        // (fully blacklisted, or excluded by the network's CFList), until one yields a channel
+        // or the whole list is spent
+        while let Some((sb, cumulative)) = self.current_bias_subband() {
+            let bank = (sb as usize - 1) * 8;
+            // only offer a channel the blacklist/network-advertised mask still allows. A bank
+            // that looks exhausted in `data` may just be transiently spent from an earlier,
+            // unbiased draw rather than actually blacklisted, so refresh it from the
+            // blacklist/network mask before giving up on this priority-list entry.
+            let biased_channel = if datarate == self.join_dr_500khz {
+                // the 500 kHz join datarate has a single channel per subband
+                let channel = 64 + (sb as usize - 1);
+                if self.available_channels.data.is_enabled(channel).unwrap() {
+                    Some(channel)
+                } else if self.available_channels.allowed_channels().is_enabled(channel).unwrap() {
+                    self.available_channels.data.set_channel(channel, true);
+                    Some(channel)
+                } else {
+                    None
+                }
+            } else {
+                self.available_channels
+                    .random_enabled_channel_in_range(rng, bank..bank + 8)
+                    .or_else(|| {
+                        self.available_channels.random_channel_in_refreshed_range(rng, bank..bank + 8)
+                    })
+            };
+
+            match biased_channel {
+                Some(channel) => {
+                    self.num_retries += 1;
+                    if self.num_retries == cumulative && cumulative == self.total_bias_retries() {
+                        // this was our last try across the priority list, so will intialize the
+                        // standard join logic with the channel we just tried. This will ensure
+                        // standard and compliant behavior when the list is a single entry with
+                        // one retry.
+                        self.available_channels.previous = Some(channel);
+                        self.available_channels.data.set_channel(channel, false);
+                    }
+                    return channel;
+                }
+                None => {
+                    // this entry's subband has nothing left to offer; spend just its share of
+                    // the budget and move on to the next entry in the priority list
+                    self.num_retries = cumulative;
                 }
-                channel
             }
-            _ => self.available_channels.get_next(rng),
         }
+        self.available_channels.get_next(rng, datarate, self.join_dr_500khz)
+    }
+
+    /// The total retry budget across the whole priority list.
+    fn total_bias_retries(&self) -> usize {
+        self.preferred_subbands.iter().map(|entry| entry.max_retries).sum()
     }
 }
 
@@ -57,6 +172,13 @@ impl JoinChannels {
 pub(crate) struct AvailableChannels {
     data: ChannelMask<9>,
     previous: Option<usize>,
+    /// The user's permanently allowed set of channels. Unlike `data`, this is never consumed by
+    /// channel selection; `reset` restores `data` from this rather than from an all-enabled mask.
+    base: ChannelMask<9>,
+    /// The most recently advertised JoinAccept CFList restriction, if any. Kept separately from
+    /// `base` so it survives a `reset` (it must, to make rejoins converge faster) without being
+    /// silently widened by `enable_channel`.
+    network_mask: Option<ChannelMask<9>>,
 }
 
 impl AvailableChannels {
@@ -70,73 +192,198 @@ impl AvailableChannels {
         true
     }
 
-    fn get_next(&mut self, rng: &mut impl RngCore) -> usize {
+    fn get_next(&mut self, rng: &mut impl RngCore, datarate: DR, dr_500khz: DR) -> usize {
         // this guarantees that there will be _some_ open channel available
         if self.is_exhausted() {
             self.reset();
         }
 
-        let channel = self.get_next_channel_inner(rng);
+        let channel = self.get_next_channel_inner(rng, datarate, dr_500khz);
         // mark the channel invalid for future selection
         self.data.set_channel(channel, false);
         self.previous = Some(channel);
         channel
     }
 
-    fn get_next_channel_inner(&mut self, rng: &mut impl RngCore) -> usize {
-        if let Some(previous) = self.previous {
+    fn get_next_channel_inner(&mut self, rng: &mut impl RngCore, datarate: DR, dr_500khz: DR) -> usize {
+        if datarate == dr_500khz {
+            // only the "fat bank" (64-71) carries 500 kHz channels, one per subband, so a draw
+            // at the region's 500 kHz datarate should stay within it rather than crossing into
+            // the 125 kHz banks. Only if the whole fat bank has been permanently disallowed (a
+            // misconfiguration: it leaves no compliant channel for this datarate at all) do we
+            // fall back across domains, to guarantee forward progress instead of panicking in
+            // embedded firmware.
+            self.random_enabled_channel_in_range(rng, 64..72)
+                .or_else(|| self.random_channel_in_refreshed_range(rng, 64..72))
+                .or_else(|| self.random_enabled_channel_in_range(rng, 0..72))
+                .expect("is_exhausted() was checked by the caller, so some channel is enabled")
+        } else if let Some(previous) = self.previous {
             // choose the next one by possibly wrapping around
             let next = (previous + 8) % 72;
             // if the channel is valid, great!
             if self.data.is_enabled(next).unwrap() {
                 next
             } else {
-                // We've wrapped around to our original random bank.
-                // Randomly select a new channel on the original bank.
-                // NB: there shall always be something because this will be the first
-                // bank to get exhausted and the caller of this function will reset
-                // when the last one is exhausted.
-                let bank = next / 8;
-                let mut entropy = rng.next_u32() as usize;
-                let mut channel = (entropy & 0b111) + bank * 8;
-                let mut entropy_used = 1;
-                loop {
-                    if self.data.is_enabled(channel).unwrap() {
-                        return channel;
-                    } else {
-                        // we've used 30 of the 32 bits of entropy. reset the byte
-                        if entropy_used == 10 {
-                            entropy = rng.next_u32() as usize;
-                            entropy_used = 0;
-                        }
-                        entropy >>= 3;
-                        entropy_used += 1;
-                        channel = (entropy & 0b111) + bank * 8;
-                    }
-                }
+                // We've wrapped around to our original random bank, or landed on some other
+                // bank that's been entirely blacklisted. Draw from the bank `next` lies in
+                // (refreshing it if it was only transiently spent); if it has nothing allowed,
+                // fall back to the 125 kHz banks (0-63) rather than the fat bank. Only if every
+                // 125 kHz channel has been permanently disallowed do we fall back across
+                // domains, to guarantee forward progress instead of panicking.
+                let bank = (next / 8) * 8;
+                self.random_enabled_channel_in_range(rng, bank..bank + 8)
+                    .or_else(|| self.random_channel_in_refreshed_range(rng, bank..bank + 8))
+                    .or_else(|| self.random_enabled_channel_in_range(rng, 0..64))
+                    .or_else(|| self.random_channel_in_refreshed_range(rng, 0..64))
+                    .or_else(|| self.random_enabled_channel_in_range(rng, 0..72))
+                    .expect("is_exhausted() was checked by the caller, so some channel is enabled")
             }
         } else {
-            // pick a completely random channel on the bottom 64
-            // NB: all channels are currently valid
-            (rng.next_u32() as usize) & 0b111111
+            // no prior selection yet (e.g. the very first join attempt, or right after a
+            // JoinAccept CFList mask reset `previous`); respect whichever channels are
+            // currently enabled rather than assuming the bottom 64 are all valid. Only if every
+            // 125 kHz channel has been permanently disallowed do we fall back across domains.
+            self.random_enabled_channel_in_range(rng, 0..64)
+                .or_else(|| self.random_channel_in_refreshed_range(rng, 0..64))
+                .or_else(|| self.random_enabled_channel_in_range(rng, 0..72))
+                .expect("is_exhausted() was checked by the caller, so some channel is enabled")
+        }
+    }
+
+    /// Pick a uniformly random enabled channel within `range`, or `None` if every channel in
+    /// `range` is disabled in `self.data`.
+    fn random_enabled_channel_in_range(
+        &self,
+        rng: &mut impl RngCore,
+        range: core::ops::Range<usize>,
+    ) -> Option<usize> {
+        let enabled_count = range.clone().filter(|&ch| self.data.is_enabled(ch).unwrap()).count();
+        if enabled_count == 0 {
+            return None;
+        }
+        let mut target = rng.next_u32() as usize % enabled_count;
+        for channel in range {
+            if self.data.is_enabled(channel).unwrap() {
+                if target == 0 {
+                    return Some(channel);
+                }
+                target -= 1;
+            }
         }
+        unreachable!("enabled_count channels were counted above, so one must have matched")
+    }
+
+    /// `range` has been fully consumed by rotation (every channel transiently marked used in
+    /// `self.data`), but some of it may still be allowed per `base`/`network_mask`. Refresh just
+    /// that slice of `data` from the allowed set and try again, instead of spilling into a
+    /// different frequency domain. Returns `None` if `range` is permanently disallowed there.
+    fn random_channel_in_refreshed_range(
+        &mut self,
+        rng: &mut impl RngCore,
+        range: core::ops::Range<usize>,
+    ) -> Option<usize> {
+        let allowed = self.allowed_channels();
+        for channel in range.clone() {
+            self.data.set_channel(channel, allowed.is_enabled(channel).unwrap());
+        }
+        self.random_enabled_channel_in_range(rng, range)
+    }
+
+    /// `base` intersected with the active `network_mask`, if any. This is the full set of
+    /// channels currently allowed, before any are consumed by rotation.
+    fn allowed_channels(&self) -> ChannelMask<9> {
+        match &self.network_mask {
+            Some(mask) => Self::intersect(&self.base, mask),
+            None => self.base.clone(),
+        }
+    }
+
+    fn intersect(a: &ChannelMask<9>, b: &ChannelMask<9>) -> ChannelMask<9> {
+        let mut result = ChannelMask::default();
+        for channel in 0..72 {
+            result.set_channel(channel, a.is_enabled(channel).unwrap() && b.is_enabled(channel).unwrap());
+        }
+        result
     }
 
     fn reset(&mut self) {
-        self.data = ChannelMask::default();
+        self.data = self.allowed_channels();
+        self.previous = None;
+    }
+
+    /// Seed `self.data` with a network-advertised mask intersected with the user's blacklist,
+    /// falling back to the full (blacklist-respecting) plan if the mask, once intersected with
+    /// the user's blacklist, advertises no usable channels at all. The mask is remembered so
+    /// it's still honored across a later `reset` (e.g. after the device loses its session and
+    /// has to rejoin).
+    fn apply_channel_mask(&mut self, mask: &ChannelMask<9>) {
+        let restricted = Self::intersect(&self.base, mask);
+        self.network_mask =
+            if restricted.as_ref().iter().all(|&byte| byte == 0) { None } else { Some(mask.clone()) };
+        self.reset();
+    }
+
+    /// Whether replacing `base` with `candidate` would leave nothing allowed at all, once
+    /// intersected with any active `network_mask`.
+    fn would_exhaust_allowed_channels(&self, candidate_base: &ChannelMask<9>) -> bool {
+        let allowed = match &self.network_mask {
+            Some(mask) => Self::intersect(candidate_base, mask),
+            None => candidate_base.clone(),
+        };
+        allowed.as_ref().iter().all(|&byte| byte == 0)
+    }
+
+    /// Permanently mark a channel as unusable, distinct from the transient marking that
+    /// `get_next` does while rotating through the plan. Refuses to do so if it would leave the
+    /// join rotation with no allowed channel at all, mirroring the same empty-set guard
+    /// `apply_channel_mask` applies to the network mask.
+    fn disable_channel(&mut self, channel: usize) {
+        let mut candidate = self.base.clone();
+        candidate.set_channel(channel, false);
+        if self.would_exhaust_allowed_channels(&candidate) {
+            return;
+        }
+        self.base = candidate;
+        self.data.set_channel(channel, false);
+    }
+
+    /// Permanently re-allow a previously blacklisted channel. If a network-advertised CFList is
+    /// currently restricting the plan, the channel only becomes selectable again if that
+    /// restriction also allows it.
+    fn enable_channel(&mut self, channel: usize) {
+        self.base.set_channel(channel, true);
+        let allowed = match &self.network_mask {
+            Some(mask) => mask.is_enabled(channel).unwrap(),
+            None => true,
+        };
+        self.data.set_channel(channel, allowed);
+    }
+
+    /// Replace the user's allowed set of channels outright. Refuses to do so if it would leave
+    /// the join rotation with no allowed channel at all, for the same reason `disable_channel`
+    /// does.
+    fn set_base(&mut self, mask: ChannelMask<9>) {
+        if self.would_exhaust_allowed_channels(&mask) {
+            return;
+        }
+        self.base = mask;
+        self.data = self.allowed_channels();
         self.previous = None;
     }
 }
 
 /// This macro implements public functions relating to a fixed plan region. This is preferred to a
 /// trait implementation because the user does not have to worry about importing the trait to make
-/// use of these functions.
+/// use of these functions. `$join_dr_500khz` is the datarate this region's fixed channel plan
+/// uses for its 500 kHz "fat bank" channels, since it differs between US915 and AU915.
 macro_rules! impl_join_bias {
-    ($region:ident) => {
+    ($region:ident, $join_dr_500khz:expr) => {
         impl $region {
             /// Create this struct directly if you want to specify a subband on which to bias the join process.
             pub fn new() -> Self {
-                Self::default()
+                let mut region = Self::default();
+                region.0.join_channels.join_dr_500khz = $join_dr_500khz;
+                region
             }
 
             /// Specify a preferred subband when joining the network. Only the first join attempt
@@ -170,12 +417,53 @@ macro_rules! impl_join_bias {
             pub fn clear_join_bias(&mut self) {
                 self.0.join_channels.clear_join_bias()
             }
+
+            /// Specify an ordered list of preferred subbands to try, in priority order, before
+            /// falling back to the standard compliant join rotation described in the US915/AU915
+            /// regional specifications. Each subband's retry budget is spent before moving on to
+            /// the next. At most 8 entries are kept.
+            pub fn set_join_bias_list(&mut self, subbands: &[(Subband, usize)]) {
+                self.0.join_channels.set_join_bias_list(subbands)
+            }
+
+            /// Permanently mark the given channels as unusable, for both join and data channel
+            /// selection. This is distinct from the transient marking that ordinary channel
+            /// rotation does; a disabled channel stays disabled across resets. Never disables the
+            /// last remaining allowed channel, since that would leave nothing to join on.
+            pub fn disable_channels(&mut self, channels: &[usize]) {
+                for &channel in channels {
+                    self.0.join_channels.disable_channel(channel);
+                }
+            }
+
+            /// Re-allow channels previously disabled with [`Self::disable_channels`].
+            pub fn enable_channels(&mut self, channels: &[usize]) {
+                for &channel in channels {
+                    self.0.join_channels.enable_channel(channel);
+                }
+            }
+
+            /// Replace the user's allowed set of channels outright. A no-op if `mask` allows no
+            /// channel that any active network-advertised mask also allows, since that would
+            /// leave nothing to join on.
+            pub fn set_channel_mask(&mut self, mask: ChannelMask<9>) {
+                self.0.join_channels.set_channel_mask(mask)
+            }
+
+            /// To be called by join-accept processing when the JoinAccept's CFList is the
+            /// ChMask variant, so that subsequent join retries are restricted to the channels
+            /// the network has advertised instead of probing the whole plan.
+            pub(crate) fn apply_join_accept_cflist(&mut self, mask: &ChannelMask<9>) {
+                self.0.join_channels.apply_channel_mask(mask)
+            }
         }
     };
 }
 
-impl_join_bias!(US915);
-impl_join_bias!(AU915);
+// US915's 500 kHz "fat bank" channels are used at DR4; AU915's at DR6. `JoinChannels` is shared
+// between the two fixed channel plans, so it must be told which one applies.
+impl_join_bias!(US915, DR::_4);
+impl_join_bias!(AU915, DR::_6);
 
 #[cfg(test)]
 mod test {
@@ -187,20 +475,20 @@ mod test {
         // run the test a bunch of times due to the rng
         for _ in 0..100 {
             let mut join_channels = JoinChannels::default();
-            let first_channel = join_channels.get_next_channel(&mut rng);
+            let first_channel = join_channels.get_next_channel(&mut rng, DR::_0);
             // the first channel is always in the bottom 64
             assert!(first_channel < 64);
-            let next_channel = join_channels.get_next_channel(&mut rng);
+            let next_channel = join_channels.get_next_channel(&mut rng, DR::_0);
             // the next channel is always incremented by 8, since we always have
             // the fat bank (channels 64-71)
             assert_eq!(next_channel, first_channel + 8);
             // we generate 6 more channels
             for _ in 0..7 {
-                let c = join_channels.get_next_channel(&mut rng);
+                let c = join_channels.get_next_channel(&mut rng, DR::_0);
                 assert!(c < 72);
             }
             // after 8 tries, we should be back at the original bank but on a different channel
-            let ninth_channel = join_channels.get_next_channel(&mut rng);
+            let ninth_channel = join_channels.get_next_channel(&mut rng, DR::_0);
             assert_eq!(ninth_channel / 8, first_channel / 8);
             assert_ne!(ninth_channel, first_channel);
         }
@@ -211,16 +499,16 @@ mod test {
         let mut rng = rand_core::OsRng;
 
         let mut join_channels = JoinChannels::default();
-        let first_channel = join_channels.get_next_channel(&mut rng);
+        let first_channel = join_channels.get_next_channel(&mut rng, DR::_0);
         // the first channel is always in the bottom 64
         assert!(first_channel < 64);
-        let next_channel = join_channels.get_next_channel(&mut rng);
+        let next_channel = join_channels.get_next_channel(&mut rng, DR::_0);
         // the next channel is always incremented by 8, since we always have
         // the fat bank (channels 64-71)
         assert_eq!(next_channel, first_channel + 8);
         // we generate 6000
         for _ in 0..6000 {
-            let c = join_channels.get_next_channel(&mut rng);
+            let c = join_channels.get_next_channel(&mut rng, DR::_0);
             assert!(c < 72);
         }
     }
@@ -232,23 +520,350 @@ mod test {
         for _ in 0..100 {
             let mut join_channels = JoinChannels::default();
             join_channels.set_join_bias(Subband::_2, 1);
-            let first_channel = join_channels.get_next_channel(&mut rng);
+            let first_channel = join_channels.get_next_channel(&mut rng, DR::_0);
             // the first is on subband 2
             assert!(first_channel > 7);
             assert!(first_channel < 16);
-            let next_channel = join_channels.get_next_channel(&mut rng);
+            let next_channel = join_channels.get_next_channel(&mut rng, DR::_0);
             // the next channel is always incremented by 8, since we always have
             // the fat bank (channels 64-71)
             assert_eq!(next_channel, first_channel + 8);
             // we generate 6 more channels
             for _ in 0..7 {
-                let c = join_channels.get_next_channel(&mut rng);
+                let c = join_channels.get_next_channel(&mut rng, DR::_0);
                 assert!(c < 72);
             }
             // after 8 tries, we should be back at the biased bank but on a different channel
-            let ninth_channel = join_channels.get_next_channel(&mut rng);
+            let ninth_channel = join_channels.get_next_channel(&mut rng, DR::_0);
             assert_eq!(ninth_channel / 8, first_channel / 8);
             assert_ne!(ninth_channel, first_channel);
         }
     }
+
+    #[test]
+    fn test_join_channels_500khz() {
+        let mut rng = rand_core::OsRng;
+        // run the test a bunch of times due to the rng
+        for _ in 0..100 {
+            let mut join_channels = JoinChannels::default();
+            // at the 500 kHz join datarate, every channel is in the fat bank
+            let channel = join_channels.get_next_channel(&mut rng, DR::_4);
+            assert!((64..72).contains(&channel));
+        }
+    }
+
+    #[test]
+    fn test_join_channels_500khz_biased() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        join_channels.set_join_bias(Subband::_3, 1);
+        // the preferred subband has exactly one 500 kHz channel
+        let channel = join_channels.get_next_channel(&mut rng, DR::_4);
+        assert_eq!(channel, 64 + (Subband::_3 as usize - 1));
+    }
+
+    #[test]
+    fn test_join_500khz_datarate_is_region_specific() {
+        let mut rng = rand_core::OsRng;
+
+        // US915's 500 kHz fat bank is used at DR4
+        let mut us915 = US915::new();
+        let channel = us915.0.join_channels.get_next_channel(&mut rng, DR::_4);
+        assert!((64..72).contains(&channel), "expected US915 DR4 to use the fat bank, got {channel}");
+
+        // AU915's 500 kHz fat bank is used at DR6, not DR4 like US915
+        let mut au915 = AU915::new();
+        let channel = au915.0.join_channels.get_next_channel(&mut rng, DR::_4);
+        assert!(channel < 64, "expected AU915 DR4 to stay in the 125 kHz banks, got {channel}");
+        let channel = au915.0.join_channels.get_next_channel(&mut rng, DR::_6);
+        assert!((64..72).contains(&channel), "expected AU915 DR6 to use the fat bank, got {channel}");
+    }
+
+    #[test]
+    fn test_apply_channel_mask_restricts_selection() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+
+        // network only enabled subband 2's 125 kHz channels (8-15)
+        let mut mask = ChannelMask::default();
+        for ch in 0..72 {
+            mask.set_channel(ch, (8..16).contains(&ch));
+        }
+        join_channels.apply_channel_mask(&mask);
+
+        for _ in 0..20 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!((8..16).contains(&channel));
+        }
+    }
+
+    #[test]
+    fn test_apply_empty_channel_mask_falls_back_to_full_plan() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+
+        let mask = ChannelMask::default();
+        let mut empty = mask.clone();
+        for ch in 0..72 {
+            empty.set_channel(ch, false);
+        }
+        join_channels.apply_channel_mask(&empty);
+
+        let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+        assert!(channel < 72);
+    }
+
+    #[test]
+    fn test_apply_channel_mask_falls_back_when_intersection_with_blacklist_is_empty() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+
+        // the user has blacklisted everything except subband 1's 125 kHz channels (0-7)
+        for channel in 8..72 {
+            join_channels.disable_channel(channel);
+        }
+
+        // the network's CFList only allows subband 2 (8-15), which is disjoint from what the
+        // blacklist allows; the intersection is empty, so the mask must be treated the same as
+        // an explicitly empty one and fall back to the (blacklist-respecting) full plan
+        let mut mask = ChannelMask::default();
+        for ch in 0..72 {
+            mask.set_channel(ch, (8..16).contains(&ch));
+        }
+        join_channels.apply_channel_mask(&mask);
+
+        for _ in 0..20 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!(channel < 8, "expected subband 1, got {channel}");
+        }
+    }
+
+    #[test]
+    fn test_region_apply_join_accept_cflist_restricts_selection() {
+        let mut rng = rand_core::OsRng;
+        let mut region = US915::new();
+
+        // network only allows subband 2's 125 kHz channels (8-15)
+        let mut mask = ChannelMask::default();
+        for ch in 0..72 {
+            mask.set_channel(ch, (8..16).contains(&ch));
+        }
+        region.apply_join_accept_cflist(&mask);
+
+        for _ in 0..20 {
+            let channel = region.0.join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!((8..16).contains(&channel));
+        }
+    }
+
+    #[test]
+    fn test_disabled_channels_are_never_selected() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+
+        // blacklist everything except subband 1's 125 kHz channels (0-7)
+        for channel in 8..72 {
+            join_channels.disable_channel(channel);
+        }
+
+        for _ in 0..20 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!(channel < 8);
+        }
+    }
+
+    #[test]
+    fn test_disabled_channel_survives_reset() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        join_channels.disable_channel(0);
+        join_channels.reset();
+
+        for _ in 0..20 {
+            assert_ne!(join_channels.get_next_channel(&mut rng, DR::_0), 0);
+        }
+    }
+
+    #[test]
+    fn test_enable_channel_reverses_disable() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        join_channels.disable_channel(0);
+        join_channels.enable_channel(0);
+
+        // channel 0 is selectable again; run a handful of rounds to give the rng a chance to
+        // land on it without asserting it must come up on the very first draw
+        let mut seen_zero = false;
+        for _ in 0..200 {
+            if join_channels.get_next_channel(&mut rng, DR::_0) == 0 {
+                seen_zero = true;
+                break;
+            }
+        }
+        assert!(seen_zero);
+    }
+
+    #[test]
+    fn test_disabling_a_whole_bank_does_not_hang() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // blacklist all of subband 5 (channels 32-39); the wrap-around rotation will eventually
+        // land on this bank and must not spin forever looking for an enabled channel in it
+        for channel in 32..40 {
+            join_channels.disable_channel(channel);
+        }
+
+        for _ in 0..100 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!(!(32..40).contains(&channel));
+        }
+    }
+
+    #[test]
+    fn test_disabling_the_fat_bank_does_not_hang_500khz_join() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // blacklist the entire fat bank; a 500 kHz join draw can no longer be satisfied there,
+        // so it must fall back to a full-plan scan instead of looping forever
+        for channel in 64..72 {
+            join_channels.disable_channel(channel);
+        }
+
+        let channel = join_channels.get_next_channel(&mut rng, DR::_4);
+        assert!(channel < 64);
+    }
+
+    #[test]
+    fn test_disabling_every_channel_does_not_panic() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // attempt to blacklist the entire plan; the last remaining allowed channel must survive
+        // so the join rotation always has something to offer
+        for channel in 0..72 {
+            join_channels.disable_channel(channel);
+        }
+
+        for _ in 0..20 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!(channel < 72);
+        }
+    }
+
+    #[test]
+    fn test_mask_restricted_rotation_never_crosses_into_the_fat_bank() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+
+        // network only enabled subband 2's 125 kHz channels (8-15); every one of these will be
+        // transiently exhausted and refreshed several times over, but a DR::_0 join must never
+        // wander into the 500 kHz fat bank (64-71) to find a channel
+        let mut mask = ChannelMask::default();
+        for ch in 0..72 {
+            mask.set_channel(ch, (8..16).contains(&ch));
+        }
+        join_channels.apply_channel_mask(&mask);
+
+        for _ in 0..50 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!((8..16).contains(&channel));
+        }
+    }
+
+    #[test]
+    fn test_join_bias_list_walks_subbands_in_order() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        join_channels.set_join_bias_list(&[(Subband::_1, 2), (Subband::_2, 2)]);
+
+        for _ in 0..2 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!(channel < 8, "expected subband 1, got {channel}");
+        }
+        for _ in 0..2 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!((8..16).contains(&channel), "expected subband 2, got {channel}");
+        }
+        // the list is now exhausted; standard rotation takes over
+        let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+        assert!(channel < 72);
+    }
+
+    #[test]
+    fn test_join_bias_list_single_entry_matches_set_join_bias() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        join_channels.set_join_bias_list(&[(Subband::_2, 1)]);
+        let first_channel = join_channels.get_next_channel(&mut rng, DR::_0);
+        assert!((8..16).contains(&first_channel));
+        let next_channel = join_channels.get_next_channel(&mut rng, DR::_0);
+        assert_eq!(next_channel, first_channel + 8);
+    }
+
+    #[test]
+    fn test_join_bias_never_selects_a_blacklisted_channel() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // blacklist everything in subband 2 except channel 9
+        for channel in (8..16).filter(|&ch| ch != 9) {
+            join_channels.disable_channel(channel);
+        }
+        join_channels.set_join_bias(Subband::_2, 50);
+
+        for _ in 0..50 {
+            assert_eq!(join_channels.get_next_channel(&mut rng, DR::_0), 9);
+        }
+    }
+
+    #[test]
+    fn test_join_bias_falls_through_when_subband_fully_blacklisted() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // blacklist the entire preferred subband
+        for channel in 8..16 {
+            join_channels.disable_channel(channel);
+        }
+        join_channels.set_join_bias(Subband::_2, 5);
+
+        for _ in 0..5 {
+            assert!(!(8..16).contains(&join_channels.get_next_channel(&mut rng, DR::_0)));
+        }
+    }
+
+    #[test]
+    fn test_join_bias_refreshes_a_transiently_exhausted_subband_instead_of_skipping_it() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // mark the whole preferred subband as transiently spent in `data`, as ordinary rotation
+        // would after drawing every one of its channels; none of this is a blacklist, so the
+        // subband is still fully allowed by `base`/`network_mask`
+        for channel in 8..16 {
+            join_channels.available_channels.data.set_channel(channel, false);
+        }
+        join_channels.set_join_bias(Subband::_2, 5);
+
+        // the subband is still fully allowed by the blacklist/network mask, so the bias draw
+        // must refresh and keep offering it rather than treating it as exhausted
+        for _ in 0..5 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!((8..16).contains(&channel), "expected subband 2, got {channel}");
+        }
+    }
+
+    #[test]
+    fn test_join_bias_list_skips_a_fully_blacklisted_entry_instead_of_the_whole_list() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        // blacklist the first preferred subband only
+        for channel in 0..8 {
+            join_channels.disable_channel(channel);
+        }
+        join_channels.set_join_bias_list(&[(Subband::_1, 2), (Subband::_2, 2)]);
+
+        // subband 1 is unusable, so every draw should come from subband 2 instead of falling
+        // all the way through to the standard compliant rotation
+        for _ in 0..2 {
+            let channel = join_channels.get_next_channel(&mut rng, DR::_0);
+            assert!((8..16).contains(&channel), "expected subband 2, got {channel}");
+        }
+    }
 }